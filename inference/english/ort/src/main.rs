@@ -1,16 +1,42 @@
+mod sampler;
+
 use anyhow::{Error as E, Result};
 use hf_hub::{api::sync::Api, Repo, RepoType};
-use ndarray::{s, Array2};
+use ndarray::{s, Array2, Array4, ArrayD};
 use ort::{
     execution_providers::DirectMLExecutionProvider,
-    inputs,
     session::{builder::GraphOptimizationLevel, Session},
 };
+use sampler::{Sampler, SamplingConfig};
+use serde::Deserialize;
 use std::{collections::HashMap, io::Write};
 use std::time::Instant;
 use tokenizers::Tokenizer;
 
+/// Just enough of the model config to shape the `past_key_values`/`present`
+/// cache tensors; the full OLMo `Config` lives in the Candle path.
+#[derive(Deserialize)]
+struct OnnxConfig {
+    num_hidden_layers: usize,
+    num_attention_heads: usize,
+    hidden_size: usize,
+    // Absent for full multi-head attention exports; set for GQA/MQA exports
+    // where the K/V cache has fewer heads than the query projection.
+    num_key_value_heads: Option<usize>,
+}
+
 fn main() -> Result<()> {
+    // 0. Parse sampling flags. Greedy (temperature 0) stays the default.
+    let args: Vec<String> = std::env::args().collect();
+    let sampling_config = SamplingConfig {
+        temperature: args.iter().find_map(|a| a.strip_prefix("--temperature=")).and_then(|v| v.parse().ok()).unwrap_or(0.0),
+        top_k: args.iter().find_map(|a| a.strip_prefix("--top-k=")).and_then(|v| v.parse().ok()),
+        top_p: args.iter().find_map(|a| a.strip_prefix("--top-p=")).and_then(|v| v.parse().ok()),
+        repetition_penalty: args.iter().find_map(|a| a.strip_prefix("--repetition-penalty=")).and_then(|v| v.parse().ok()).unwrap_or(1.0),
+        seed: args.iter().find_map(|a| a.strip_prefix("--seed=")).and_then(|v| v.parse().ok()).unwrap_or(42),
+    };
+    let mut sampler = Sampler::new(sampling_config);
+
     // 1. Initialize ONNX Runtime with DirectML execution provider
     println!("Initializing ONNX Runtime with DirectML...");
     ort::init()
@@ -27,11 +53,17 @@ fn main() -> Result<()> {
     
     let tokenizer_filename = repo.get("tokenizer.json")?;
     let model_filename = repo.get("model.onnx")?; // ONNX Runtime requires the pre-compiled graph
+    let config_filename = repo.get("config.json")?;
 
     // 3. Load Tokenizer
     println!("Loading tokenizer...");
     let tokenizer = Tokenizer::from_file(tokenizer_filename)
         .map_err(|e| E::msg(e.to_string()))?;
+    let onnx_config: OnnxConfig = serde_json::from_reader(std::fs::File::open(config_filename)?)?;
+    let head_dim = onnx_config.hidden_size / onnx_config.num_attention_heads;
+    // GQA/MQA exports keep fewer K/V heads than query heads; a plain MHA
+    // export has no `num_key_value_heads` field and uses the query count.
+    let num_kv_heads = onnx_config.num_key_value_heads.unwrap_or(onnx_config.num_attention_heads);
 
     // 4 & 5. Load Configuration and Model Weights
     println!("Loading ONNX model into DirectML session (this may take a moment)...");
@@ -45,57 +77,83 @@ fn main() -> Result<()> {
     let encoding = tokenizer.encode(prompt, false).map_err(|e| E::msg(e.to_string()))?;
     let mut tokens = encoding.get_ids().to_vec();
 
-    // 7. Generation Loop (Greedy Decoding)
+    // 7. Generation Loop (Greedy Decoding) with incremental KV-cache stepping.
     let max_tokens_to_generate = 200;
-    
+
+    // past_key_values.{layer}.{key,value}: seeded empty ([batch,
+    // num_kv_heads, 0, head_dim]) on the first step, then replaced every step
+    // with the matching `present.*` output so each later step only has to
+    // process the single newly sampled token instead of the whole growing
+    // sequence. This loop assumes the export takes attention_mask-derived
+    // positions internally and has no separate `position_ids` input; an
+    // export that requires one would need it threaded through alongside
+    // `input_ids` below.
+    let mut past_key_values: HashMap<String, ArrayD<f32>> = HashMap::new();
+    for layer in 0..onnx_config.num_hidden_layers {
+        let empty = Array4::<f32>::zeros((1, num_kv_heads, 0, head_dim)).into_dyn();
+        past_key_values.insert(format!("past_key_values.{}.key", layer), empty.clone());
+        past_key_values.insert(format!("past_key_values.{}.value", layer), empty);
+    }
+    let mut past_len = 0usize;
+
     let mut first_token_time = std::time::Duration::default();
     let mut subsequent_tokens_time = std::time::Duration::default();
     let mut last_token_time = std::time::Duration::default();
-    
+
     for i in 0..max_tokens_to_generate {
         let start = Instant::now();
-        
-        // Convert tokens to an ndarray of shape [batch_size, sequence_length]
-        // ONNX LLMs typically expect input_ids as INT64
-        let input_ids_i64: Vec<i64> = tokens.iter().map(|&t| t as i64).collect();
-        let input_array = Array2::from_shape_vec((1, tokens.len()), input_ids_i64)?;
-        
-        // Attention mask of 1s (matching the length of the input)
-        let attention_mask = Array2::from_elem((1, tokens.len()), 1i64);
-
-        // Forward pass. 
-        // Note: Raw ONNX requires explicit KV cache state management for efficient single-token 
-        // stepping. For simplicity and parity with the naive loop, we pass the full sequence here.
-        let inputs: HashMap<&str, ort::value::Value> = inputs! {
-            "input_ids" => ort::value::Value::from_array(input_array.into_dyn())?,
-            "attention_mask" => ort::value::Value::from_array(attention_mask.into_dyn())?
-        }?;
+
+        // Only the prompt itself is fed on the first step; every step after
+        // that feeds just the one token sampled last time, relying on
+        // `past_key_values` for everything before it.
+        let step_ids: Vec<i64> = if i == 0 {
+            tokens.iter().map(|&t| t as i64).collect()
+        } else {
+            vec![*tokens.last().unwrap() as i64]
+        };
+        let step_len = step_ids.len();
+        let input_array = Array2::from_shape_vec((1, step_len), step_ids)?;
+
+        // Attention mask covers the whole sequence seen so far: past tokens
+        // plus this step's tokens, growing by one each step.
+        let attention_mask = Array2::from_elem((1, past_len + step_len), 1i64);
+
+        let mut inputs: HashMap<String, ort::value::Value> = HashMap::new();
+        inputs.insert("input_ids".to_string(), ort::value::Value::from_array(input_array.into_dyn())?.into());
+        inputs.insert("attention_mask".to_string(), ort::value::Value::from_array(attention_mask.into_dyn())?.into());
+        for (name, array) in &past_key_values {
+            inputs.insert(name.clone(), ort::value::Value::from_array(array.clone())?.into());
+        }
 
         let outputs = session.run(inputs)?;
-                
+
         // Extract the logits tensor (typically f32)
         let logits = outputs["logits"].try_extract_tensor::<f32>()?;
         let logits_view = logits.view();
-        
+
         // Shape is usually [batch_size, sequence_length, vocab_size]
         let seq_len = logits_view.shape()[1];
-        
+
         // Extract the logits for the very last token in the sequence
         let next_token_logits = logits_view.slice(s![0, seq_len - 1, ..]);
-        
-        // Greedily select the token ID with the highest probability (Argmax)
-        let mut max_val = f32::NEG_INFINITY;
-        let mut next_token_id = 0u32;
-        
-        for (idx, &val) in next_token_logits.iter().enumerate() {
-            if val > max_val {
-                max_val = val;
-                next_token_id = idx as u32;
+
+        // Sample the next token ID (greedy argmax when temperature is 0)
+        let next_token_id = sampler.sample(&next_token_logits.to_vec(), &tokens);
+
+        tokens.push(next_token_id);
+        past_len += step_len;
+
+        // Feed this step's `present.*` outputs back as next step's `past`.
+        for layer in 0..onnx_config.num_hidden_layers {
+            for field in ["key", "value"] {
+                let present = outputs[format!("present.{}.{}", layer, field).as_str()]
+                    .try_extract_tensor::<f32>()?
+                    .view()
+                    .to_owned();
+                past_key_values.insert(format!("past_key_values.{}.{}", layer, field), present);
             }
         }
-        
-        tokens.push(next_token_id);
-        
+
         // Decode and print the new token
         if let Ok(text) = tokenizer.decode(&[next_token_id], false) {
             print!("{}", text);
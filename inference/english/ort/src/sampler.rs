@@ -0,0 +1,114 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Sampling knobs exposed as CLI flags so a user can trade off novelty
+/// versus coherence in generated continuations. `temperature == 0.0` is
+/// greedy argmax, the previous hard-coded behavior.
+pub struct SamplingConfig {
+    pub temperature: f64,
+    pub top_k: Option<usize>,
+    pub top_p: Option<f64>,
+    pub repetition_penalty: f32,
+    pub seed: u64,
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            temperature: 0.0,
+            top_k: None,
+            top_p: None,
+            repetition_penalty: 1.0,
+            seed: 42,
+        }
+    }
+}
+
+/// Applies temperature, top-k/top-p filtering, and a repetition penalty
+/// before drawing from the resulting distribution with a seedable RNG.
+pub struct Sampler {
+    config: SamplingConfig,
+    rng: StdRng,
+}
+
+impl Sampler {
+    pub fn new(config: SamplingConfig) -> Self {
+        let rng = StdRng::seed_from_u64(config.seed);
+        Self { config, rng }
+    }
+
+    /// Picks the next token ID given raw logits and the tokens already
+    /// emitted (used for the repetition penalty).
+    pub fn sample(&mut self, logits: &[f32], already_emitted: &[u32]) -> u32 {
+        if self.config.temperature <= 0.0 {
+            return Self::argmax(logits);
+        }
+
+        let mut logits = logits.to_vec();
+        if self.config.repetition_penalty != 1.0 {
+            for &token in already_emitted {
+                if let Some(l) = logits.get_mut(token as usize) {
+                    *l = if *l > 0.0 {
+                        *l / self.config.repetition_penalty
+                    } else {
+                        *l * self.config.repetition_penalty
+                    };
+                }
+            }
+        }
+        for l in logits.iter_mut() {
+            *l /= self.config.temperature as f32;
+        }
+
+        let mut probs = Self::softmax(&logits);
+        probs.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+
+        if let Some(k) = self.config.top_k {
+            probs.truncate(k.max(1));
+        }
+        if let Some(p) = self.config.top_p {
+            let mut cumulative = 0f32;
+            let mut cutoff = probs.len();
+            for (i, &(_, prob)) in probs.iter().enumerate() {
+                cumulative += prob;
+                if cumulative >= p as f32 {
+                    cutoff = i + 1;
+                    break;
+                }
+            }
+            probs.truncate(cutoff.max(1));
+        }
+
+        let total: f32 = probs.iter().map(|(_, p)| p).sum();
+        let mut target = self.rng.gen::<f32>() * total;
+        for &(token, prob) in &probs {
+            target -= prob;
+            if target <= 0.0 {
+                return token;
+            }
+        }
+        probs.last().map(|&(token, _)| token).unwrap_or(0)
+    }
+
+    fn argmax(logits: &[f32]) -> u32 {
+        let mut max_val = f32::NEG_INFINITY;
+        let mut max_idx = 0u32;
+        for (idx, &val) in logits.iter().enumerate() {
+            if val > max_val {
+                max_val = val;
+                max_idx = idx as u32;
+            }
+        }
+        max_idx
+    }
+
+    /// Returns `(token_id, probability)` pairs for every logit.
+    fn softmax(logits: &[f32]) -> Vec<(u32, f32)> {
+        let max_logit = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let exps: Vec<f32> = logits.iter().map(|&l| (l - max_logit).exp()).collect();
+        let sum: f32 = exps.iter().sum();
+        exps.into_iter()
+            .enumerate()
+            .map(|(idx, e)| (idx as u32, e / sum))
+            .collect()
+    }
+}
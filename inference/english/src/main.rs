@@ -1,13 +1,27 @@
+mod sampler;
+
 use anyhow::{Error as E, Result};
 use candle_core::{DType, Device, Tensor};
 use candle_nn::VarBuilder;
 use candle_transformers::models::olmo::{Config, Model};
 use hf_hub::{api::sync::Api, Repo, RepoType};
+use sampler::{Sampler, SamplingConfig};
 use std::io::Write;
 use std::time::Instant;
 use tokenizers::Tokenizer;
 
 fn main() -> Result<()> {
+    // 0. Parse sampling flags. Greedy (temperature 0) stays the default.
+    let args: Vec<String> = std::env::args().collect();
+    let sampling_config = SamplingConfig {
+        temperature: args.iter().find_map(|a| a.strip_prefix("--temperature=")).and_then(|v| v.parse().ok()).unwrap_or(0.0),
+        top_k: args.iter().find_map(|a| a.strip_prefix("--top-k=")).and_then(|v| v.parse().ok()),
+        top_p: args.iter().find_map(|a| a.strip_prefix("--top-p=")).and_then(|v| v.parse().ok()),
+        repetition_penalty: args.iter().find_map(|a| a.strip_prefix("--repetition-penalty=")).and_then(|v| v.parse().ok()).unwrap_or(1.0),
+        seed: args.iter().find_map(|a| a.strip_prefix("--seed=")).and_then(|v| v.parse().ok()).unwrap_or(42),
+    };
+    let mut sampler = Sampler::new(sampling_config);
+
     // 1. Setup hardware acceleration based on your Cargo.toml features
     let device = Device::Cpu;
     #[cfg(feature = "cuda")]
@@ -64,15 +78,20 @@ fn main() -> Result<()> {
         // println!("\rGenerating token {}/{}...", i + 1, max_tokens_to_generate);
         // std::io::stdout().flush()?;
         
-        // Forward pass. No KV cacheing for now.
+        // Forward pass. `input` holds only the newly sampled token (the full
+        // prompt on the very first step); the model keeps its own KV cache
+        // internally and `pos` tells it where in that cache this step lands,
+        // so cost per step stays roughly constant instead of growing with
+        // the sequence length. A new prompt would need a fresh `Model`
+        // instance to start from an empty cache.
         let logits = model.forward(&input, pos)?;
-        pos += 1;
+        pos += input.dim(1)?;
                
         // Extract the logits for the very last token in the sequence
         let next_token_logits = logits.squeeze(0)?.get(logits.dim(1)? - 1)?;
-        
-        // Greedily select the token ID with the highest probability
-        let next_token_id = next_token_logits.argmax(0)?.to_scalar::<u32>()?;
+
+        // Sample the next token ID (greedy argmax when temperature is 0)
+        let next_token_id = sampler.sample(&next_token_logits.to_vec1::<f32>()?, &tokens);
         tokens.push(next_token_id);
         
         // Decode and print the new token as soon as it is generated
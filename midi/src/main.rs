@@ -1,25 +1,417 @@
-use anyhow::Result;
+use anyhow::{Error as E, Result};
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::olmo::{Config, Model};
+use hf_hub::{api::sync::Api, Repo, RepoType};
 use midir::{Ignore, MidiInput, MidiInputConnection, MidiOutput, MidiOutputConnection};
-use std::io::{stdin, stdout, Write};
+use std::io::{stdin, stdout, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc;
 use std::sync::{Arc, Mutex};
+use tokenizers::Tokenizer;
+
+/// Generation parameters that can be changed remotely through the control
+/// connection while the callbacks that read them keep running.
+struct NetworkState {
+    temperature: f64,
+    max_tokens_to_generate: usize,
+    generation_enabled: bool,
+}
+
+impl Default for NetworkState {
+    fn default() -> Self {
+        Self { temperature: 0.0, max_tokens_to_generate: 32, generation_enabled: true }
+    }
+}
+
+/// Starts a background task that accepts newline-framed feed subscribers, and
+/// a second background task that owns the client list and writes lines sent
+/// over the returned channel out to every subscriber. Socket I/O (accepting
+/// and writing) only ever happens on these two threads, never on the
+/// real-time midir callback that calls `broadcast_feed`.
+fn spawn_feed_server(addr: &str) -> Result<mpsc::Sender<String>> {
+    let listener = TcpListener::bind(addr)?;
+    println!("Streaming tokenized MIDI feed on {}", addr);
+    let clients: Arc<Mutex<Vec<TcpStream>>> = Arc::new(Mutex::new(Vec::new()));
+    let accept_clients = clients.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            if let Ok(stream) = stream {
+                accept_clients.lock().unwrap().push(stream);
+            }
+        }
+    });
+
+    let (tx, rx) = mpsc::channel::<String>();
+    std::thread::spawn(move || {
+        for line in rx {
+            let mut clients = clients.lock().unwrap();
+            clients.retain_mut(|client| writeln!(client, "{}", line.trim_end()).is_ok());
+        }
+    });
+    Ok(tx)
+}
+
+/// Hands one line of the tokenized history off to the feed server's writer
+/// thread. Never touches a socket itself, so a slow or backpressured
+/// subscriber can't stall the real-time callback this is called from.
+fn broadcast_feed(feed_tx: &Option<mpsc::Sender<String>>, line: &str) {
+    if let Some(tx) = feed_tx {
+        let _ = tx.send(line.to_string());
+    }
+}
+
+/// Starts a background task that accepts control connections and applies a
+/// small text command set to the shared generation/transport state: `temperature
+/// <f64>`, `max_tokens <usize>`, `start`, `stop`, `reset_clock`.
+fn spawn_control_server(
+    addr: &str,
+    state: Arc<Mutex<NetworkState>>,
+    sender: Arc<Mutex<MidiSender>>,
+) -> Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    println!("Accepting remote control commands on {}", addr);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+            let state = state.clone();
+            let sender = sender.clone();
+            std::thread::spawn(move || {
+                for line in BufReader::new(stream).lines() {
+                    let line = match line {
+                        Ok(line) => line,
+                        Err(_) => break,
+                    };
+                    handle_control_command(&line, &state, &sender);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+/// Forwards a monitored note-on/note-off message straight to `sender`'s
+/// output connection, transposing by `transpose` semitones (clamped to the
+/// 0-127 MIDI note range) and rewriting the channel nibble to
+/// `MidiSender.channel`, so a player can audition rerouted input alongside
+/// the monitoring output (and any model-generated notes sharing the port).
+fn forward_note(message: &[u8], transpose: i8, sender: &Arc<Mutex<MidiSender>>) {
+    if message.len() != 3 {
+        return;
+    }
+    let status = message[0] & 0xf0;
+    if status != 0x80 && status != 0x90 {
+        return;
+    }
+    let note = (message[1] as i16 + transpose as i16).clamp(0, 127) as u8;
+    let velocity = message[2];
+
+    let mut sender = sender.lock().unwrap();
+    let result = if status == 0x90 && velocity > 0 {
+        sender.send_note_on(note, velocity)
+    } else {
+        sender.send_note_off(note)
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to forward MIDI thru event: {}", e);
+    }
+}
+
+fn handle_control_command(line: &str, state: &Arc<Mutex<NetworkState>>, sender: &Arc<Mutex<MidiSender>>) {
+    let mut parts = line.split_whitespace();
+    match parts.next() {
+        Some("temperature") => {
+            if let Some(value) = parts.next().and_then(|v| v.parse::<f64>().ok()) {
+                state.lock().unwrap().temperature = value;
+            }
+        }
+        Some("max_tokens") => {
+            if let Some(value) = parts.next().and_then(|v| v.parse::<usize>().ok()) {
+                state.lock().unwrap().max_tokens_to_generate = value;
+            }
+        }
+        Some("start") => state.lock().unwrap().generation_enabled = true,
+        Some("stop") => state.lock().unwrap().generation_enabled = false,
+        Some("reset_clock") => sender.lock().unwrap().reset_tick(),
+        Some(other) => eprintln!("Unknown control command: {}", other),
+        None => {}
+    }
+}
+
+/// A single decoded MIDI event, scheduled to fire some number of clock ticks
+/// in the future relative to the event before it.
+#[derive(Clone, Copy, Debug)]
+enum MidiEvent {
+    NoteOn(u8, u8),
+    NoteOff(u8),
+}
 
 struct MidiSender {
     conn: Option<MidiOutputConnection>,
     clock_conn: Option<MidiInputConnection<()>>,
     channel: u8,
+    tick: u32,
+    // Pending events, each keyed by the absolute tick at which it should fire.
+    // Kept sorted by tick so `handle_clock` only ever looks at the front.
+    queue: Vec<(u32, MidiEvent)>,
 }
 
 impl MidiSender {
     fn new(conn: Option<MidiOutputConnection>, channel: u8) -> Self {
-        Self { conn, clock_conn: None, channel }
+        Self { conn, clock_conn: None, channel, tick: 0, queue: Vec::new() }
     }
 
     fn set_clock_input(&mut self, conn: MidiInputConnection<()>) {
         self.clock_conn = Some(conn);
     }
 
+    /// Resets the 24-PPQN tick counter and drops anything still scheduled,
+    /// for the remote `reset_clock` control command.
+    fn reset_tick(&mut self) {
+        self.tick = 0;
+        self.queue.clear();
+    }
+
+    /// Enqueues a sequence of (delta_ticks, event) pairs, as produced by
+    /// `MidiTokenDecoder::decode`, relative to the current tick.
+    fn schedule(&mut self, events: Vec<(u32, MidiEvent)>) {
+        let mut due = self.tick;
+        for (delta, event) in events {
+            due += delta;
+            self.queue.push((due, event));
+        }
+        self.queue.sort_by_key(|(due, _)| *due);
+    }
+
+    /// Advances the 24-PPQN clock by one tick and dispatches any events that
+    /// have come due, keeping playback locked to the clock source rather than
+    /// wall-clock time.
     fn handle_clock(&mut self) {
-        // Listen for clock
+        self.tick += 1;
+        while let Some(&(due, _)) = self.queue.first() {
+            if due > self.tick {
+                break;
+            }
+            let (_, event) = self.queue.remove(0);
+            if let Err(e) = self.dispatch(event) {
+                eprintln!("Failed to send scheduled MIDI event: {}", e);
+            }
+        }
+    }
+
+    fn dispatch(&mut self, event: MidiEvent) -> Result<()> {
+        match event {
+            MidiEvent::NoteOn(note, velocity) => self.send_note_on(note, velocity),
+            MidiEvent::NoteOff(note) => self.send_note_off(note),
+        }
+    }
+
+    fn send_note_on(&mut self, note: u8, velocity: u8) -> Result<()> {
+        if let Some(conn) = self.conn.as_mut() {
+            conn.send(&[0x90 | (self.channel & 0x0f), note, velocity])?;
+        }
+        Ok(())
+    }
+
+    fn send_note_off(&mut self, note: u8) -> Result<()> {
+        if let Some(conn) = self.conn.as_mut() {
+            conn.send(&[0x80 | (self.channel & 0x0f), note, 0])?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses the `Nxx`/`vxx`/`Oxx`/`Pxx`/`Q`/`Start`/`End` token grammar emitted
+/// by `MidiHandler::handle_message` back into timed MIDI events, the inverse
+/// of the forward encoder.
+struct MidiTokenDecoder {
+    pending_velocity: u8,
+    // A note-on whose pitch has been read but whose trailing `v` token
+    // hasn't arrived yet (the encoder always emits them as an "Nxx vxx"
+    // pair), plus the delta that was pending when the note arrived.
+    pending_note: Option<(u8, u32)>,
+    // Ticks accumulated since the last quarter boundary, so a `Q` token (an
+    // absolute "clock_counter hit 24" event) can be turned back into the
+    // ticks it actually represents rather than a flat 24.
+    ticks_since_quarter: u32,
+}
+
+impl MidiTokenDecoder {
+    fn new() -> Self {
+        Self { pending_velocity: 0x40, pending_note: None, ticks_since_quarter: 0 }
+    }
+
+    /// Decodes `text` into (delta_ticks, event) pairs, where `delta_ticks` is
+    /// the number of 24-PPQN clock ticks to wait after the previous event
+    /// before firing this one.
+    fn decode(&mut self, text: &str) -> Vec<(u32, MidiEvent)> {
+        let mut events = Vec::new();
+        let mut pending_delta = 0u32;
+        for tok in text.split_whitespace() {
+            if let Some(hex) = tok.strip_prefix('P') {
+                if let Ok(ticks) = u32::from_str_radix(hex, 16) {
+                    pending_delta += ticks;
+                    self.ticks_since_quarter += ticks;
+                }
+            } else if tok == "Q" {
+                // `get_delta_time` resets the encoder's delta counter on every
+                // note/SysEx event, so a quarter boundary may arrive after
+                // fewer than 24 ticks if one already fired since the last Q;
+                // only the remainder to the boundary is still outstanding.
+                pending_delta += 24u32.saturating_sub(self.ticks_since_quarter);
+                self.ticks_since_quarter = 0;
+            } else if tok == "Start" || tok == "End" {
+                // Transport markers carry no note data, but Start implies the
+                // encoder's clock_counter was reset too.
+                if tok == "Start" {
+                    self.ticks_since_quarter = 0;
+                }
+            } else if let Some(hex) = tok.strip_prefix('v') {
+                if let Ok(v) = u8::from_str_radix(hex, 16) {
+                    // The encoder always emits velocity right after the note
+                    // it belongs to ("Nxx vxx "), so pair it with that note
+                    // rather than the next one.
+                    if let Some((note, delta)) = self.pending_note.take() {
+                        events.push((delta, MidiEvent::NoteOn(note, v)));
+                    }
+                    self.pending_velocity = v;
+                }
+            } else if let Some(hex) = tok.strip_prefix('N') {
+                if let Ok(note) = u8::from_str_radix(hex, 16) {
+                    // Flush any note left over from a truncated "Nxx" with no
+                    // trailing velocity before starting a new one.
+                    if let Some((note, delta)) = self.pending_note.take() {
+                        events.push((delta, MidiEvent::NoteOn(note, self.pending_velocity)));
+                    }
+                    self.pending_note = Some((note, pending_delta));
+                    pending_delta = 0;
+                }
+            } else if let Some(hex) = tok.strip_prefix('O') {
+                if let Ok(note) = u8::from_str_radix(hex, 16) {
+                    events.push((pending_delta, MidiEvent::NoteOff(note)));
+                    pending_delta = 0;
+                }
+            }
+        }
+        if let Some((note, delta)) = self.pending_note.take() {
+            events.push((delta, MidiEvent::NoteOn(note, self.pending_velocity)));
+        }
+        events
+    }
+}
+
+/// Bridges the tokenized MIDI history into OLMo and plays the continuation
+/// back out through a `MidiSender`, turning monitoring into call-and-response.
+struct GenerationBridge {
+    tokenizer: Tokenizer,
+    config: Config,
+    vb: VarBuilder<'static>,
+    device: Device,
+    decoder: MidiTokenDecoder,
+    max_history_tokens: usize,
+    network_state: Arc<Mutex<NetworkState>>,
+    // History length as of the last generation, so an unchanged history
+    // (the 500ms poll finding nothing new) doesn't re-run the model and
+    // re-schedule the same continuation on top of itself.
+    last_history_len: usize,
+}
+
+impl GenerationBridge {
+    fn new(network_state: Arc<Mutex<NetworkState>>) -> Result<Self> {
+        let device = Device::Cpu;
+
+        let api = Api::new()?;
+        let repo = api.repo(Repo::new("allenai/OLMo-1B-hf".to_string(), RepoType::Model));
+        let config_filename = repo.get("config.json")?;
+        let tokenizer_filename = repo.get("tokenizer.json")?;
+        let weights_filename = repo.get("model.safetensors")?;
+
+        let tokenizer = Tokenizer::from_file(tokenizer_filename).map_err(|e| E::msg(e.to_string()))?;
+        let config: Config = serde_json::from_reader(std::fs::File::open(config_filename)?)?;
+        let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[weights_filename], DType::F32, &device)? };
+
+        Ok(Self {
+            tokenizer,
+            config,
+            vb,
+            device,
+            decoder: MidiTokenDecoder::new(),
+            max_history_tokens: 256,
+            network_state,
+            last_history_len: 0,
+        })
+    }
+
+    /// Tokenizes the recent history and extends it with the model, returning
+    /// the continuation decoded into timed events for the caller to schedule.
+    /// Does not touch `MidiSender` itself, so the (possibly multi-second)
+    /// forward loop below never holds up the clock/thru path waiting on that
+    /// lock. Reads `temperature`/`max_tokens_to_generate`/
+    /// `generation_enabled` from `network_state` so a remote control
+    /// connection can steer it live. A fresh `Model` is built every call so
+    /// each generation starts from an empty KV cache instead of concatenating
+    /// onto whatever the previous call left behind, and a no-op is returned
+    /// when `history` hasn't grown since the last call.
+    fn generate(&mut self, history: &[String]) -> Result<Vec<(u32, MidiEvent)>> {
+        let (temperature, max_tokens_to_generate, enabled) = {
+            let state = self.network_state.lock().unwrap();
+            (state.temperature, state.max_tokens_to_generate, state.generation_enabled)
+        };
+        if !enabled || history.len() <= self.last_history_len {
+            return Ok(Vec::new());
+        }
+        self.last_history_len = history.len();
+
+        let start = history.len().saturating_sub(self.max_history_tokens);
+        let prompt: String = history[start..].concat();
+        if prompt.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut model = Model::new(&self.config, self.vb.clone())?;
+        let encoding = self.tokenizer.encode(prompt.as_str(), false).map_err(|e| E::msg(e.to_string()))?;
+        let mut tokens = encoding.get_ids().to_vec();
+        let mut pos = 0;
+        let mut input = Tensor::new(tokens.as_slice(), &self.device)?.unsqueeze(0)?;
+        let mut generated = Vec::new();
+
+        for _ in 0..max_tokens_to_generate {
+            let logits = model.forward(&input, pos)?;
+            pos += input.dim(1)?;
+            let next_token_logits = logits.squeeze(0)?.get(logits.dim(1)? - 1)?;
+            let next_token_id = if temperature > 0.0 {
+                Self::sample_with_temperature(&next_token_logits.to_vec1::<f32>()?, temperature)
+            } else {
+                next_token_logits.argmax(0)?.to_scalar::<u32>()?
+            };
+            tokens.push(next_token_id);
+            generated.push(next_token_id);
+            input = Tensor::new(&[next_token_id], &self.device)?.unsqueeze(0)?;
+        }
+
+        let continuation = self.tokenizer.decode(&generated, false).map_err(|e| E::msg(e.to_string()))?;
+        Ok(self.decoder.decode(&continuation))
+    }
+
+    /// Scales logits by `temperature`, softmaxes them, and draws a token ID
+    /// from the resulting distribution.
+    fn sample_with_temperature(logits: &[f32], temperature: f64) -> u32 {
+        let scaled: Vec<f32> = logits.iter().map(|&l| l / temperature as f32).collect();
+        let max_logit = scaled.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let exps: Vec<f32> = scaled.iter().map(|&l| (l - max_logit).exp()).collect();
+        let sum: f32 = exps.iter().sum();
+
+        let mut target = rand::random::<f32>() * sum;
+        for (idx, &exp) in exps.iter().enumerate() {
+            target -= exp;
+            if target <= 0.0 {
+                return idx as u32;
+            }
+        }
+        (exps.len() - 1) as u32
     }
 }
 
@@ -27,17 +419,50 @@ struct MidiHandler {
     clock_counter: u32,
     delta_clock_counter: u32,
     history: Vec<String>,
+    // Accumulates 0xf0..0xf7 SysEx bytes across callback invocations, since
+    // midir may deliver a single SysEx message in several chunks.
+    sysex_buffer: Vec<u8>,
+    in_sysex: bool,
 }
 
 impl MidiHandler {
     fn new() -> Self {
-        Self { 
+        Self {
             clock_counter: 0,
             delta_clock_counter: 0,
             history: Vec::new(),
+            sysex_buffer: Vec::new(),
+            in_sysex: false,
+        }
+    }
+
+    /// Maps a SysEx manufacturer ID byte to a human-readable name, to help
+    /// recognize patch/parameter dumps like the Juno-106's.
+    fn manufacturer_name(manufacturer_id: u8) -> &'static str {
+        match manufacturer_id {
+            0x41 => "Roland",
+            0x42 => "Korg",
+            0x43 => "Yamaha",
+            0x40 => "Kawai",
+            _ => "Unknown",
         }
     }
 
+    /// Decodes a fully-captured SysEx buffer (0xf0 ... 0xf7) into the
+    /// `S<manufacturer-id> <len> <data>` token form, so patch changes become
+    /// part of the tokenized context instead of a blind spot.
+    fn decode_sysex(buffer: &[u8]) -> String {
+        let manufacturer_id = buffer.get(1).copied().unwrap_or(0);
+        let mut s = format!("S{:02x} {:02x} ", manufacturer_id, buffer.len());
+        // Surface the decoded parameter bytes between the manufacturer ID and
+        // the terminating 0xf7.
+        for byte in buffer.iter().skip(2).take(buffer.len().saturating_sub(3)) {
+            s.push_str(&format!("{:02x}", byte));
+        }
+        s.push(' ');
+        s
+    }
+
     fn increment_clock(&mut self) -> Option<String> {
         self.clock_counter += 1;
         self.delta_clock_counter += 1;
@@ -70,12 +495,12 @@ impl MidiHandler {
         // http://www.opensound.com/pguide/midi/midi5.html
         if message.len() == 0 {
             return None; // Ignore empty messages.
-        } else if message[0] == 0xf0 {
-            return None; // Ignore SysEx messages.
-        } else if message[0] & 0xf0 == 0xb0 {
-            // Juno 106 sends these when all keys are lifted.
-            return None; // Ignore Control Change messages.
         } else if message.len() == 1 && message[0] == 0xf8 {
+            // Real-time status bytes (0xf8-0xff) never enter the SysEx
+            // buffering below: the clock pulse is handled here, Start/End
+            // just after, and the rest fall to the ignore guard. Devices interleave
+            // clock pulses mid-dump, and if we fed them into sysex_buffer
+            // instead we'd both corrupt the capture and drop the tick.
             if let Some(s) = self.increment_clock() {
                 output.push_str(&s);
             }
@@ -87,9 +512,33 @@ impl MidiHandler {
         } else if message.len() == 1 && message[0] == 0xfc {
             // End of sequence.
             output.push_str("End ");
-        } else if message[0] & 0xf0 == 0xf0 {
-            return None; // Ignore other system real-time messages (Active Sensing, etc).
-        } else if message.len() == 3 && 
+        } else if (self.in_sysex && !(message.len() == 1 && message[0] >= 0xf8)) || message[0] == 0xf0 {
+            // Must run ahead of the generic real-time/common ignore guard
+            // below: ALSA/CoreMIDI routinely split a SysEx dump into
+            // arbitrary chunks, including delivering the terminating 0xf7 as
+            // its own single-byte message. That byte matches the guard
+            // (0xf7 & 0xf0 == 0xf0, 0xf7 != 0xf0), so checking `in_sysex`
+            // first is what lets the terminator still reach the buffer.
+            if message[0] == 0xf0 {
+                self.sysex_buffer.clear();
+                self.in_sysex = true;
+            }
+            self.sysex_buffer.extend_from_slice(message);
+            if self.sysex_buffer.last() == Some(&0xf7) {
+                self.in_sysex = false;
+                let manufacturer_id = self.sysex_buffer.get(1).copied().unwrap_or(0);
+                println!("Captured SysEx dump from {}", Self::manufacturer_name(manufacturer_id));
+                output.push_str(&self.get_delta_time());
+                output.push_str(&Self::decode_sysex(&self.sysex_buffer));
+            } else {
+                return None; // Still waiting on the terminating 0xf7.
+            }
+        } else if message[0] & 0xf0 == 0xf0 && message[0] != 0xf0 {
+            return None; // Ignore other system real-time/common messages (Active Sensing, etc).
+        } else if message[0] & 0xf0 == 0xb0 {
+            // Juno 106 sends these when all keys are lifted.
+            return None; // Ignore Control Change messages.
+        } else if message.len() == 3 &&
             (message[0] & 0xf0 == 0x90 && message[2] == 0) || 
             (message.len() == 3 && message[0] & 0xf0 == 0x80) {
           // Note off message (Note on with velocity 0) - print the note number.
@@ -146,6 +595,21 @@ fn main() -> Result<()> {
     let clock_port_name = args.iter()
         .find(|a| a.starts_with("--clock="))
         .map(|a| a.trim_start_matches("--clock="));
+    let feed_addr = args.iter()
+        .find(|a| a.starts_with("--feed-addr="))
+        .map(|a| a.trim_start_matches("--feed-addr="))
+        .unwrap_or("127.0.0.1:7878")
+        .to_string();
+    let control_addr = args.iter()
+        .find(|a| a.starts_with("--control-addr="))
+        .map(|a| a.trim_start_matches("--control-addr="))
+        .unwrap_or("127.0.0.1:7879")
+        .to_string();
+    let thru = args.iter().any(|a| a == "--thru");
+    let transpose: i8 = args.iter()
+        .find(|a| a.starts_with("--transpose="))
+        .and_then(|a| a.trim_start_matches("--transpose=").parse().ok())
+        .unwrap_or(0);
 
     let midi_out = MidiOutput::new("midi_out_client")?;
     let out_ports = midi_out.ports();
@@ -167,13 +631,29 @@ fn main() -> Result<()> {
 
     let sender = Arc::new(Mutex::new(MidiSender::new(out_conn, 1)));
 
+    // Mirror the tokenized feed to subscribers and accept remote generation
+    // commands, so the model/inference host doesn't need to be the same
+    // machine that's wired to the MIDI gear.
+    let network_state = Arc::new(Mutex::new(NetworkState::default()));
+    let feed_tx = match spawn_feed_server(&feed_addr) {
+        Ok(tx) => Some(tx),
+        Err(e) => {
+            eprintln!("Could not start feed server on {}: {}", feed_addr, e);
+            None
+        }
+    };
+    if let Err(e) = spawn_control_server(&control_addr, network_state.clone(), sender.clone()) {
+        eprintln!("Could not start control server on {}: {}", control_addr, e);
+    }
+
     // Setup clock input
+    let mut external_clock_connected = false;
     if let Some(clock_name) = clock_port_name {
         let mut midi_in_clock = MidiInput::new("midi_clock_in")?;
         midi_in_clock.ignore(Ignore::None);
         let ports = midi_in_clock.ports();
         let clock_port = ports.iter().find(|p| midi_in_clock.port_name(p).ok().as_deref() == Some(clock_name));
-        
+
         if let Some(p) = clock_port {
              let sender_weak = Arc::downgrade(&sender);
              let conn = midi_in_clock.connect(
@@ -188,14 +668,30 @@ fn main() -> Result<()> {
                  },
                  ()
              ).map_err(|e| anyhow::anyhow!("Failed to connect to clock {}: {}", clock_name, e))?;
-             
+
              sender.lock().unwrap().set_clock_input(conn);
              println!("Connected to clock source '{}'", clock_name);
+             external_clock_connected = true;
         } else {
              println!("Clock port '{}' not found.", clock_name);
         }
     }
 
+    if !external_clock_connected {
+        // No external --clock= source: drive the 24-PPQN scheduler off an
+        // internal timer instead, so scheduled playback still advances.
+        let sender_weak = Arc::downgrade(&sender);
+        let tick_interval = std::time::Duration::from_secs_f64(60.0 / 120.0 / 24.0);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(tick_interval);
+            match sender_weak.upgrade() {
+                Some(sender_arc) => sender_arc.lock().unwrap().handle_clock(),
+                None => break,
+            }
+        });
+        println!("No --clock= source connected; using an internal 120 BPM clock.");
+    }
+
     // 2. Connect to each port.
     // We need to keep the input connections alive in a vector, otherwise they will be dropped and closed.
     let mut connections = Vec::new();
@@ -218,14 +714,20 @@ fn main() -> Result<()> {
         if let Some(p) = port {
             println!("Connecting to '{}'...", name);
             let handler = handler.clone();
-            
+            let feed_tx = feed_tx.clone();
+            let thru_sender = sender.clone();
+
             let conn = midi_in.connect(
                 &p,
                 "midi_monitor_in",
                 move |_stamp, message, _| {
+                    if thru {
+                        forward_note(message, transpose, &thru_sender);
+                    }
                     if let Some(s) = handler.lock().unwrap().handle_message(message) {
                         print!("{}", s);
                         let _ = stdout().flush();
+                        broadcast_feed(&feed_tx, &s);
                     }
                 },
                 (),
@@ -237,6 +739,31 @@ fn main() -> Result<()> {
         }
     }
 
+    // Spawn the generation bridge: it periodically tokenizes the accumulated
+    // history, extends it with OLMo, and plays the continuation back out
+    // through the output port so the tool responds rather than just monitors.
+    match GenerationBridge::new(network_state.clone()) {
+        Ok(mut bridge) => {
+            let handler = handler.clone();
+            let sender = sender.clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(std::time::Duration::from_millis(500));
+                let history = handler.lock().unwrap().history.clone();
+                match bridge.generate(&history) {
+                    Ok(events) => {
+                        if !events.is_empty() {
+                            sender.lock().unwrap().schedule(events);
+                        }
+                    }
+                    Err(e) => eprintln!("Generation error: {}", e),
+                }
+            });
+        }
+        Err(e) => {
+            eprintln!("Could not load generation model ({}); continuing in monitor-only mode.", e);
+        }
+    }
+
     println!("\nListening for MIDI events... Press Enter to exit.");
     let mut input = String::new();
     stdin().read_line(&mut input)?;